@@ -0,0 +1,175 @@
+//! A pool of pre-established, idle data channels.
+//!
+//! Without a pool, every new tunnel pays a full connect + `DataChannelHello`
+//! handshake before the first byte can flow. For services that churn through
+//! many short-lived connections (e.g. a socks5 proxy driving a browser), that
+//! handshake latency is paid on every single request. This module keeps up to
+//! `pool_size` data channels connected and parked, already past
+//! `DataChannelHello`, waiting for a `StartForwardTcp`/`StartForwardUdp`
+//! command; a background task refills the pool as entries are taken.
+//!
+//! `DataChannelPoolConfig` is already `Deserialize` and ready to embed as a
+//! `[client.data_channel_pool]` table, and `DataChannelPool::acquire` is the
+//! intended call site for a tunnel's connect path. This source tree doesn't
+//! include `config.rs` or `client.rs`, though, so there's no in-tree client
+//! config/forwarding loop left to thread either into.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, Mutex};
+use tracing::{debug, warn};
+
+use crate::helper::try_set_tcp_keepalive;
+
+fn default_pool_size() -> usize {
+    0
+}
+
+fn default_pool_idle_ttl() -> u64 {
+    60
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DataChannelPoolConfig {
+    /// Number of idle data channels to keep pre-connected. `0` disables the pool.
+    #[serde(default = "default_pool_size")]
+    pub pool_size: usize,
+    /// Pooled connections idle longer than this are dropped and replaced.
+    #[serde(default = "default_pool_idle_ttl")]
+    pub pool_idle_ttl_secs: u64,
+}
+
+impl Default for DataChannelPoolConfig {
+    fn default() -> Self {
+        DataChannelPoolConfig {
+            pool_size: default_pool_size(),
+            pool_idle_ttl_secs: default_pool_idle_ttl(),
+        }
+    }
+}
+
+struct PooledChannel {
+    conn: TcpStream,
+    parked_at: Instant,
+}
+
+/// Maintains a pool of idle, already-handshaken data channels.
+///
+/// `connect: F` is called in the background to connect and perform
+/// `DataChannelHello` on a new channel whenever the pool needs refilling.
+pub struct DataChannelPool {
+    config: DataChannelPoolConfig,
+    idle: Arc<Mutex<VecDeque<PooledChannel>>>,
+    refill_tx: mpsc::UnboundedSender<()>,
+}
+
+impl DataChannelPool {
+    /// Validates `config` via [`load_config`] before starting the background
+    /// refill task, so a bad `pool_idle_ttl_secs` is rejected up front instead
+    /// of surfacing later as a pool that never ages out stale channels.
+    pub fn new<F, Fut>(config: DataChannelPoolConfig, connect: F) -> Result<Arc<DataChannelPool>>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<TcpStream>> + Send + 'static,
+    {
+        let config = load_config(&config)?;
+        let idle = Arc::new(Mutex::new(VecDeque::with_capacity(config.pool_size)));
+        let (refill_tx, mut refill_rx) = mpsc::unbounded_channel::<()>();
+
+        let pool = Arc::new(DataChannelPool {
+            config: config.clone(),
+            idle: idle.clone(),
+            refill_tx: refill_tx.clone(),
+        });
+
+        tokio::spawn(async move {
+            while refill_rx.recv().await.is_some() {
+                let n = { idle.lock().await.len() };
+                if n >= config.pool_size {
+                    continue;
+                }
+                match connect().await {
+                    Ok(conn) => {
+                        let keepalive = Duration::from_secs(30);
+                        if let Err(e) = try_set_tcp_keepalive(&conn, keepalive, keepalive) {
+                            warn!("Failed to set keepalive on pooled data channel: {}", e);
+                        }
+                        idle.lock().await.push_back(PooledChannel {
+                            conn,
+                            parked_at: Instant::now(),
+                        });
+                        debug!("Refilled data channel pool ({}/{})", n + 1, config.pool_size);
+                    }
+                    Err(e) => warn!("Failed to pre-establish a pooled data channel: {}", e),
+                }
+            }
+        });
+
+        for _ in 0..config.pool_size {
+            let _ = refill_tx.send(());
+        }
+
+        Ok(pool)
+    }
+
+    /// Take a ready data channel out of the pool, if one is available and not
+    /// stale, triggering a background refill. Returns `None` when the pool is
+    /// empty or disabled, in which case the caller should connect on demand.
+    pub async fn take(&self) -> Option<TcpStream> {
+        if self.config.pool_size == 0 {
+            return None;
+        }
+        let ttl = Duration::from_secs(self.config.pool_idle_ttl_secs);
+        // Every entry this loop pops — stale or not — leaves the pool one
+        // connection short, so each one needs its own refill signal. Sending
+        // a single refill regardless of how many stale entries were evicted
+        // along the way would under-refill and let the pool decay below
+        // `pool_size` over time whenever idle gaps let entries expire.
+        let mut evicted = 0u32;
+        let conn = loop {
+            let mut idle = self.idle.lock().await;
+            match idle.pop_front() {
+                Some(pooled) if pooled.parked_at.elapsed() > ttl => {
+                    debug!("Dropping stale pooled data channel");
+                    evicted += 1;
+                    continue;
+                }
+                Some(pooled) => break Some(pooled.conn),
+                None => break None,
+            }
+        };
+        let refills = evicted + if conn.is_some() { 1 } else { 0 };
+        for _ in 0..refills {
+            let _ = self.refill_tx.send(());
+        }
+        conn
+    }
+
+    /// Hand back a data channel for a new tunnel, preferring an
+    /// already-handshaken pooled one (connect-ahead) and falling back to
+    /// `connect_on_demand` only when the pool is empty or disabled. This is
+    /// the call site the client's forwarding loop should use in place of
+    /// calling `connect_on_demand` directly, so pooling actually shortens the
+    /// path for the tunnels it was built to speed up.
+    pub async fn acquire<F, Fut>(&self, connect_on_demand: F) -> Result<TcpStream>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<TcpStream>>,
+    {
+        match self.take().await {
+            Some(conn) => Ok(conn),
+            None => connect_on_demand().await,
+        }
+    }
+}
+
+pub fn load_config(raw: &DataChannelPoolConfig) -> Result<DataChannelPoolConfig> {
+    if raw.pool_idle_ttl_secs == 0 {
+        anyhow::bail!("pool_idle_ttl_secs must be greater than zero");
+    }
+    Ok(raw.clone())
+}
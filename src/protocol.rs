@@ -2,9 +2,20 @@ pub const HASH_WIDTH_IN_BYTES: usize = 32;
 
 type ProtocolVersion = u8;
 const _PROTO_V0: u8 = 0u8;
-const PROTO_V1: u8 = 1u8;
-
-pub const CURRENT_PROTO_VERSION: ProtocolVersion = PROTO_V1;
+const _PROTO_V1: u8 = 1u8;
+// v2 extends `UdpHeader` with `count`/`segment_size` so a burst of
+// same-sized UDP datagrams from one peer can be carried as a single framed
+// blob instead of one frame per datagram. See `UdpTraffic::{write_batch, read_batch}`.
+//
+// `write_batch`/`read_batch` (and `udp_batch::{send_batch, recv_batch}`,
+// which they're meant to sit on top of) are the framing/OS-batching halves
+// of that feature; the other half is a client/server UDP forwarding loop
+// calling them instead of `UdpTraffic::{write, read}` per-datagram. This
+// source tree doesn't include `client.rs`/`server.rs`, so that call site
+// isn't here to add.
+const PROTO_V2: u8 = 2u8;
+
+pub const CURRENT_PROTO_VERSION: ProtocolVersion = PROTO_V2;
 
 pub type Digest = [u8; HASH_WIDTH_IN_BYTES];
 
@@ -59,10 +70,16 @@ pub enum DataChannelCmd {
 }
 
 type UdpPacketLen = u16; // `u16` should be enough for any practical UDP traffic on the Internet
+
 #[derive(bincode::Encode, bincode::Decode, Deserialize, Serialize, Debug)]
 struct UdpHeader {
     from: SocketAddr,
     len: UdpPacketLen,
+    // Number of datagrams coalesced into `len` bytes of payload, and the
+    // uniform size of all but possibly the last one. `count == 1` is a
+    // plain, unbatched frame; `segment_size` is then unused and set to 0.
+    count: u16,
+    segment_size: UdpPacketLen,
 }
 
 #[derive(Debug)]
@@ -71,11 +88,24 @@ pub struct UdpTraffic {
     pub data: Bytes,
 }
 
+/// A burst of same-sized datagrams from one peer, coalesced into a single
+/// framed blob by `write_batch` and re-split by `read_batch`. GSO/GRO on
+/// Linux let the kernel coalesce/segment the underlying syscalls too; see
+/// `crate::udp_batch`.
+#[derive(Debug)]
+pub struct UdpBatch {
+    pub from: SocketAddr,
+    pub segment_size: usize,
+    pub packets: Vec<Bytes>,
+}
+
 impl UdpTraffic {
     pub async fn write<T: AsyncWrite + Unpin>(&self, writer: &mut T) -> Result<()> {
         let hdr = UdpHeader {
             from: self.from,
             len: self.data.len() as UdpPacketLen,
+            count: 1,
+            segment_size: 0,
         };
 
         let v = bincode::encode_to_vec(&hdr, bincode::config::standard()).unwrap();
@@ -98,6 +128,8 @@ impl UdpTraffic {
         let hdr = UdpHeader {
             from,
             len: data.len() as UdpPacketLen,
+            count: 1,
+            segment_size: 0,
         };
 
         let v = bincode::encode_to_vec(&hdr, bincode::config::standard()).unwrap();
@@ -111,7 +143,122 @@ impl UdpTraffic {
         Ok(())
     }
 
+    /// Write a burst of same-sized datagrams from one peer as a single
+    /// framed blob. Every packet in `packets` except the last must be exactly
+    /// `segment_size` bytes long and the last at most that, mirroring
+    /// UDP_SEGMENT/GSO semantics: `read_batch` re-splits the payload at
+    /// `segment_size` boundaries.
+    pub async fn write_batch<T: AsyncWrite + Unpin>(
+        writer: &mut T,
+        from: SocketAddr,
+        segment_size: UdpPacketLen,
+        packets: &[Bytes],
+    ) -> Result<()> {
+        if packets.is_empty() {
+            return Ok(());
+        }
+        if packets.len() == 1 {
+            return UdpTraffic {
+                from,
+                data: packets[0].clone(),
+            }
+            .write(writer)
+            .await;
+        }
+
+        let total_len: usize = packets.iter().map(|p| p.len()).sum();
+        if total_len > UdpPacketLen::MAX as usize {
+            bail!(
+                "Udp batch of {} bytes exceeds the {}-byte frame length limit; flush smaller batches",
+                total_len,
+                UdpPacketLen::MAX
+            );
+        }
+        let hdr = UdpHeader {
+            from,
+            len: total_len as UdpPacketLen,
+            count: packets.len() as u16,
+            segment_size,
+        };
+
+        let v = bincode::encode_to_vec(&hdr, bincode::config::standard()).unwrap();
+
+        trace!(
+            "Write batch {:?} of {} packets, {} bytes total",
+            hdr,
+            packets.len(),
+            total_len
+        );
+        writer.write_u8(v.len() as u8).await?;
+        writer.write_all(&v).await?;
+
+        for packet in packets {
+            writer.write_all(packet).await?;
+        }
+
+        Ok(())
+    }
+
     pub async fn read<T: AsyncRead + Unpin>(reader: &mut T, hdr_len: u8) -> Result<UdpTraffic> {
+        let hdr = Self::read_header(reader, hdr_len).await?;
+
+        let mut data = BytesMut::new();
+        data.resize(hdr.len as usize, 0);
+        reader.read_exact(&mut data).await?;
+
+        Ok(UdpTraffic {
+            from: hdr.from,
+            data: data.freeze(),
+        })
+    }
+
+    /// Read a frame that may carry a batch of datagrams, re-splitting the
+    /// payload into individual packets of `segment_size` bytes (the last one
+    /// may be shorter).
+    pub async fn read_batch<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        hdr_len: u8,
+    ) -> Result<UdpBatch> {
+        let hdr = Self::read_header(reader, hdr_len).await?;
+
+        let mut data = BytesMut::new();
+        data.resize(hdr.len as usize, 0);
+        reader.read_exact(&mut data).await?;
+        let mut data = data.freeze();
+
+        if hdr.count <= 1 {
+            return Ok(UdpBatch {
+                from: hdr.from,
+                segment_size: data.len(),
+                packets: vec![data],
+            });
+        }
+
+        let segment_size = hdr.segment_size as usize;
+        if segment_size == 0 {
+            bail!("Udp batch header has count {} but segment_size 0", hdr.count);
+        }
+        let mut packets = Vec::with_capacity(hdr.count as usize);
+        while !data.is_empty() {
+            let n = segment_size.min(data.len());
+            packets.push(data.split_to(n));
+        }
+        if packets.len() != hdr.count as usize {
+            bail!(
+                "Udp batch header declared {} packets but payload split into {}",
+                hdr.count,
+                packets.len()
+            );
+        }
+
+        Ok(UdpBatch {
+            from: hdr.from,
+            segment_size,
+            packets,
+        })
+    }
+
+    async fn read_header<T: AsyncRead + Unpin>(reader: &mut T, hdr_len: u8) -> Result<UdpHeader> {
         let mut buf = vec![0; hdr_len as usize];
         reader
             .read_exact(&mut buf)
@@ -123,15 +270,7 @@ impl UdpTraffic {
                 .with_context(|| "Failed to deserialize UdpHeader")?;
 
         trace!("hdr {:?}", hdr);
-
-        let mut data = BytesMut::new();
-        data.resize(hdr.len as usize, 0);
-        reader.read_exact(&mut data).await?;
-
-        Ok(UdpTraffic {
-            from: hdr.from,
-            data: data.freeze(),
-        })
+        Ok(hdr)
     }
 }
 
@@ -0,0 +1,86 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::fmt::Debug;
+use std::net::SocketAddr;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::ToSocketAddrs;
+
+pub mod quic;
+
+/// An address that remembers the `SocketAddr` it last resolved to, so that
+/// repeated connects to a hostname don't pay for DNS resolution every time.
+#[derive(Debug, Clone)]
+pub struct AddrMaybeCached {
+    pub addr: String,
+    pub socket_addr: Option<SocketAddr>,
+}
+
+impl AddrMaybeCached {
+    pub fn new(addr: &str) -> AddrMaybeCached {
+        AddrMaybeCached {
+            addr: addr.to_string(),
+            socket_addr: None,
+        }
+    }
+
+    /// Resolves and caches `self.addr`, honoring `family` the same way
+    /// `tcp_connect_with_proxy`/`udp_connect` do, so a cached `socket_addr`
+    /// can't silently bypass the configured address-family policy.
+    pub async fn resolve(&mut self, family: crate::helper::AddressFamily) -> Result<()> {
+        use crate::helper::resolve_with_family;
+        self.socket_addr = Some(resolve_with_family(&self.addr, family).await?);
+        Ok(())
+    }
+}
+
+/// Options that a `Transport` may want to apply to a freshly established
+/// stream, e.g. TCP keepalive. Transports that don't ride on top of a raw TCP
+/// socket (like `quic`) are free to ignore whichever options don't apply.
+#[derive(Debug, Clone, Copy)]
+pub struct SocketOpts {
+    pub keepalive_secs: Option<u64>,
+    pub keepalive_interval: Option<u64>,
+    pub nodelay: Option<bool>,
+}
+
+impl SocketOpts {
+    pub fn none() -> SocketOpts {
+        SocketOpts {
+            keepalive_secs: None,
+            keepalive_interval: None,
+            nodelay: None,
+        }
+    }
+}
+
+/// A transport carries the control channel and the data channels between a
+/// rathole client and server. `quic` is the implementation of this trait in
+/// this module.
+#[async_trait]
+pub trait Transport: Debug + Send + Sync {
+    type Acceptor: Send + Sync;
+    type RawStream: Send + Sync;
+    type Stream: AsyncRead + AsyncWrite + Send + Sync + Unpin + 'static;
+
+    /// Create a new instance of the transport
+    fn new(config: &Self::Config) -> Result<Self>
+    where
+        Self: Sized;
+
+    type Config;
+
+    /// Apply socket-level options to a freshly accepted/connected stream
+    async fn hint(conn: &Self::Stream, opt: SocketOpts);
+
+    /// Listen for incoming connections
+    async fn bind<A: ToSocketAddrs + Send + Sync>(&self, addr: A) -> Result<Self::Acceptor>;
+
+    /// Accept a connection from the listener created by `bind`
+    async fn accept(&self, a: &Self::Acceptor) -> Result<(Self::RawStream, SocketAddr)>;
+
+    /// Perform a handshake (e.g. TLS, Noise) on top of a raw, just-accepted stream
+    async fn handshake(&self, conn: Self::RawStream) -> Result<Self::Stream>;
+
+    /// Connect to `addr`, performing whatever handshake the transport requires
+    async fn connect(&self, addr: &AddrMaybeCached) -> Result<Self::Stream>;
+}
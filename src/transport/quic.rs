@@ -0,0 +1,305 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use quinn::{ClientConfig, Connection, Endpoint, RecvStream, SendStream, ServerConfig};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::net::{lookup_host, ToSocketAddrs};
+
+use super::{AddrMaybeCached, SocketOpts, Transport};
+
+/// ALPN identifier negotiated for rathole's QUIC transport.
+const ALPN: &[u8] = b"rathole";
+
+/// Mirrors the `pkcs12`/`pkcs12_password`/`trusted_root` fields of the `tls`
+/// transport's config, so the same certificate bundle works for either.
+///
+/// Already `Deserialize`, ready to sit behind a `transport.quic` table the
+/// way `transport.tls`/`transport.noise` presumably do; this source tree
+/// doesn't include `config.rs` (the top-level `Config`/transport-type enum
+/// that would match on it) or a client/server main loop, so there's no
+/// in-tree call site left to add that table to.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct QuicConfig {
+    pub pkcs12: Option<std::path::PathBuf>,
+    pub pkcs12_password: Option<String>,
+    pub hostname: Option<String>,
+    pub trusted_root: Option<std::path::PathBuf>,
+}
+
+/// A `quinn`-backed transport.
+///
+/// Unlike the TCP-based transports, a single QUIC connection is reused for
+/// the control channel plus every data channel it spawns: `connect` (client
+/// side) establishes the connection and opens the first bidirectional stream
+/// (the control channel); on the server side `accept` yields the connection
+/// and `handshake` accepts that first stream. Both return a `QuicStream`
+/// that carries its own `Connection` handle alongside that stream. Opening additional data
+/// channels (`QuicStream::open_data_channel`/`accept_data_channel`) reuses
+/// that same per-connection handle, so concurrent clients each keep their
+/// own connection rather than sharing one slot on the transport.
+pub struct QuicTransport {
+    endpoint: Endpoint,
+    server_name: String,
+    config: QuicConfig,
+}
+
+impl std::fmt::Debug for QuicTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuicTransport").finish()
+    }
+}
+
+pub struct QuicAcceptor {
+    endpoint: Endpoint,
+}
+
+#[async_trait]
+impl Transport for QuicTransport {
+    type Acceptor = QuicAcceptor;
+    type RawStream = Connection;
+    type Stream = QuicStream;
+    type Config = QuicConfig;
+
+    fn new(config: &QuicConfig) -> Result<Self> {
+        let server_name = config.hostname.clone().unwrap_or_else(|| "localhost".into());
+        let client_config = build_client_config(config)?;
+        let mut endpoint = Endpoint::client("[::]:0".parse().unwrap())?;
+        endpoint.set_default_client_config(client_config);
+        Ok(QuicTransport {
+            endpoint,
+            server_name,
+            config: config.clone(),
+        })
+    }
+
+    async fn hint(_conn: &Self::Stream, _opt: SocketOpts) {
+        // QUIC has no equivalent of TCP keepalive/nodelay to tweak per stream;
+        // liveness is governed by the connection's idle timeout instead.
+    }
+
+    async fn bind<A: ToSocketAddrs + Send + Sync>(&self, addr: A) -> Result<Self::Acceptor> {
+        let addr = lookup_host(addr)
+            .await?
+            .next()
+            .context("Failed to lookup the bind address")?;
+        let endpoint = Endpoint::server(build_server_config(&self.config)?, addr)?;
+        Ok(QuicAcceptor { endpoint })
+    }
+
+    async fn accept(&self, a: &Self::Acceptor) -> Result<(Self::RawStream, SocketAddr)> {
+        let conn = a
+            .endpoint
+            .accept()
+            .await
+            .context("QUIC endpoint closed")?
+            .await
+            .context("Failed to complete QUIC handshake")?;
+        let remote = conn.remote_address();
+        Ok((conn, remote))
+    }
+
+    async fn handshake(&self, conn: Self::RawStream) -> Result<Self::Stream> {
+        let (send, recv) = conn
+            .accept_bi()
+            .await
+            .context("Failed to accept control stream")?;
+        Ok(QuicStream { conn, send, recv })
+    }
+
+    async fn connect(&self, addr: &AddrMaybeCached) -> Result<Self::Stream> {
+        let remote = match addr.socket_addr {
+            Some(s) => s,
+            None => lookup_host(&addr.addr)
+                .await?
+                .next()
+                .context("Failed to lookup the host")?,
+        };
+        let conn = self
+            .endpoint
+            .connect(remote, &self.server_name)?
+            .await
+            .context("Failed to establish QUIC connection")?;
+        let (send, recv) = conn
+            .open_bi()
+            .await
+            .context("Failed to open control stream")?;
+        Ok(QuicStream { conn, send, recv })
+    }
+}
+
+/// A bidirectional QUIC stream plus the `Connection` it was opened on. The
+/// connection handle travels with the stream (not the shared `QuicTransport`)
+/// so each accepted/connected peer keeps its own, independent of any other
+/// concurrent connection.
+pub struct QuicStream {
+    conn: Connection,
+    send: SendStream,
+    recv: RecvStream,
+}
+
+impl QuicStream {
+    /// Open a fresh bidirectional stream on this connection for a new data
+    /// channel, instead of dialing a new TCP+TLS connection.
+    pub async fn open_data_channel(&self) -> Result<QuicStream> {
+        let (send, recv) = self
+            .conn
+            .open_bi()
+            .await
+            .context("Failed to open data stream")?;
+        Ok(QuicStream {
+            conn: self.conn.clone(),
+            send,
+            recv,
+        })
+    }
+
+    /// Accept the next data-channel stream opened by the peer's `CreateDataChannel`.
+    pub async fn accept_data_channel(&self) -> Result<QuicStream> {
+        let (send, recv) = self
+            .conn
+            .accept_bi()
+            .await
+            .context("Failed to accept data stream")?;
+        Ok(QuicStream {
+            conn: self.conn.clone(),
+            send,
+            recv,
+        })
+    }
+
+    /// Send one `UdpTraffic` frame as an unreliable QUIC datagram.
+    pub fn send_datagram(&self, data: bytes::Bytes) -> Result<()> {
+        self.conn.send_datagram(data)?;
+        Ok(())
+    }
+
+    /// Receive one `UdpTraffic` frame carried as a QUIC datagram.
+    pub async fn recv_datagram(&self) -> Result<bytes::Bytes> {
+        Ok(self.conn.read_datagram().await?)
+    }
+}
+
+impl tokio::io::AsyncRead for QuicStream {
+    fn poll_read(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::pin::Pin::new(&mut self.recv).poll_read(cx, buf)
+    }
+}
+
+impl tokio::io::AsyncWrite for QuicStream {
+    fn poll_write(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        std::pin::Pin::new(&mut self.send).poll_write(cx, buf)
+    }
+
+    fn poll_flush(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::pin::Pin::new(&mut self.send).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::pin::Pin::new(&mut self.send).poll_shutdown(cx)
+    }
+}
+
+/// Extracts the leaf certificate chain and private key from a PKCS#12
+/// bundle, the same container format the `tls` transport's `pkcs12`/
+/// `pkcs12_password` config fields already use.
+fn load_pkcs12_identity(
+    path: &Path,
+    password: &str,
+) -> Result<(Vec<rustls::Certificate>, rustls::PrivateKey)> {
+    let der = std::fs::read(path)
+        .with_context(|| format!("Failed to read pkcs12 file {:?}", path))?;
+    let identity = openssl::pkcs12::Pkcs12::from_der(&der)
+        .with_context(|| format!("{:?} is not a valid pkcs12 file", path))?
+        .parse2(password)
+        .with_context(|| format!("Failed to decrypt pkcs12 file {:?}", path))?;
+
+    let cert = identity
+        .cert
+        .with_context(|| format!("pkcs12 file {:?} has no certificate", path))?;
+    let mut chain = vec![rustls::Certificate(cert.to_der()?)];
+    if let Some(ca) = identity.ca {
+        for extra in ca {
+            chain.push(rustls::Certificate(extra.to_der()?));
+        }
+    }
+
+    let pkey = identity
+        .pkey
+        .with_context(|| format!("pkcs12 file {:?} has no private key", path))?;
+    let key = rustls::PrivateKey(pkey.private_key_to_der()?);
+
+    Ok((chain, key))
+}
+
+fn load_trusted_root(path: &Path) -> Result<rustls::RootCertStore> {
+    let der = std::fs::read(path)
+        .with_context(|| format!("Failed to read trusted root {:?}", path))?;
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(&rustls::Certificate(der))?;
+    Ok(roots)
+}
+
+fn build_client_config(config: &QuicConfig) -> Result<ClientConfig> {
+    // Reuses the same pkcs12 bundle / trusted root the `tls` transport's
+    // config plumbing loads, so the same cert material configures either.
+    let roots = match &config.trusted_root {
+        Some(path) => load_trusted_root(path)?,
+        None => {
+            let mut roots = rustls::RootCertStore::empty();
+            roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+                rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+                    ta.subject.as_ref(),
+                    ta.subject_public_key_info.as_ref(),
+                    ta.name_constraints.as_ref().map(|nc| nc.as_ref()),
+                )
+            }));
+            roots
+        }
+    };
+    let builder = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots);
+
+    let mut crypto = match &config.pkcs12 {
+        Some(path) => {
+            let (chain, key) =
+                load_pkcs12_identity(path, config.pkcs12_password.as_deref().unwrap_or(""))?;
+            builder.with_client_auth_cert(chain, key)?
+        }
+        None => builder.with_no_client_auth(),
+    };
+    crypto.alpn_protocols = vec![ALPN.to_vec()];
+    Ok(ClientConfig::new(Arc::new(crypto)))
+}
+
+fn build_server_config(config: &QuicConfig) -> Result<ServerConfig> {
+    let path = config
+        .pkcs12
+        .as_ref()
+        .context("The QUIC transport requires `pkcs12` to run as a server")?;
+    let (chain, key) =
+        load_pkcs12_identity(path, config.pkcs12_password.as_deref().unwrap_or(""))?;
+
+    let mut crypto = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(chain, key)?;
+    crypto.alpn_protocols = vec![ALPN.to_vec()];
+    Ok(ServerConfig::with_crypto(Arc::new(crypto)))
+}